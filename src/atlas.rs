@@ -0,0 +1,184 @@
+//! Shelf-packed texture atlas for fonts and icon images.
+//!
+//! Incoming RGBA images are packed into a small number of fixed-size pages so
+//! that draw commands sharing a page can skip redundant texture binds. Pages
+//! themselves never grow once allocated; when an image doesn't fit any
+//! existing page, a fresh one is allocated starting at [`DEFAULT_PAGE_SIZE`]
+//! and doubled further still if the image itself is larger than that, the
+//! same way pathfinder sizes its atlas pages.
+//!
+//! Baked font atlases are the one exception: nuklear computes each glyph
+//! quad's texture coordinates in `0..1` space relative to whichever texture
+//! the atlas handle is bound to, so a font image can't share a shelf with
+//! anything else without invalidating those coordinates. [`Atlas::insert_whole`]
+//! gives it an exclusive page sized to fit instead.
+
+use crate::device::Device;
+
+/// Starting page dimensions in pixels for a freshly allocated page; doubled
+/// further still if the image that triggered the allocation is larger than
+/// that. Existing pages are never resized.
+const DEFAULT_PAGE_SIZE: usize = 2048;
+
+/// Gap, in pixels, left between neighbouring shelf-packed rects so a
+/// `with_linear()` sampler blending across an edge picks up blank page
+/// background instead of bleeding into the next image.
+const ATLAS_PADDING: usize = 1;
+
+/// Location of a packed image within an [`Atlas`], in normalized 0..1 UV
+/// space local to its page.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AtlasRect {
+    pub page: usize,
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+/// A single horizontal shelf within a page: a run of images of similar
+/// height, packed left to right.
+struct Shelf {
+    x: usize,
+    y: usize,
+    height: usize,
+}
+
+struct Page<D: Device> {
+    texture: D::Texture,
+    width: usize,
+    height: usize,
+    shelves: Vec<Shelf>,
+    next_y: usize,
+}
+
+impl<D: Device> Page<D> {
+    fn new(device: &D, width: usize, height: usize) -> Self {
+        // `create_texture` uploads `width * height * 4` bytes of initial
+        // data, so the page needs a real (zeroed) buffer here, not `&[]`.
+        let blank = vec![0u8; width * height * 4];
+        Self {
+            texture: device.create_texture(width, height, &blank),
+            width,
+            height,
+            shelves: Vec::new(),
+            next_y: 0,
+        }
+    }
+
+    /// Picks the shelf whose height is the smallest that still fits `h` and
+    /// has room for `w`, opening a new shelf (or failing) otherwise. Leaves
+    /// [`ATLAS_PADDING`] of blank space after the rect so it doesn't bleed
+    /// into whatever gets packed next to it.
+    fn insert(&mut self, w: usize, h: usize) -> Option<(usize, usize)> {
+        let mut best: Option<usize> = None;
+        for (i, shelf) in self.shelves.iter().enumerate() {
+            if h <= shelf.height && shelf.x + w <= self.width {
+                if best.map_or(true, |b| shelf.height < self.shelves[b].height) {
+                    best = Some(i);
+                }
+            }
+        }
+
+        if let Some(i) = best {
+            let shelf = &mut self.shelves[i];
+            let x = shelf.x;
+            shelf.x += w + ATLAS_PADDING;
+            return Some((x, shelf.y));
+        }
+
+        if self.next_y + h <= self.height {
+            let y = self.next_y;
+            self.next_y += h + ATLAS_PADDING;
+            self.shelves.push(Shelf {
+                x: w + ATLAS_PADDING,
+                y,
+                height: h,
+            });
+            return Some((0, y));
+        }
+
+        None
+    }
+
+    fn rect(&self, page: usize, x: usize, y: usize, w: usize, h: usize) -> AtlasRect {
+        AtlasRect {
+            page,
+            u0: x as f32 / self.width as f32,
+            v0: y as f32 / self.height as f32,
+            u1: (x + w) as f32 / self.width as f32,
+            v1: (y + h) as f32 / self.height as f32,
+        }
+    }
+}
+
+/// Packs RGBA images into a small number of fixed-size GL texture pages
+/// using shelf packing, returning a UV sub-rectangle for each insertion.
+pub struct Atlas<D: Device> {
+    pages: Vec<Page<D>>,
+}
+
+impl<D: Device> Default for Atlas<D> {
+    fn default() -> Self {
+        Self { pages: Vec::new() }
+    }
+}
+
+impl<D: Device> Atlas<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packs a tightly-packed RGBA8 image of `width` x `height` pixels into
+    /// whichever page has room, allocating (and growing) a new page if none
+    /// do.
+    pub fn insert(&mut self, device: &D, image: &[u8], width: usize, height: usize) -> AtlasRect {
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = page.insert(width, height) {
+                device.sub_upload_texture(&page.texture, x, y, width, height, image);
+                return page.rect(index, x, y, width, height);
+            }
+        }
+
+        let mut size = DEFAULT_PAGE_SIZE;
+        while width > size || height > size {
+            size *= 2;
+        }
+        let mut page = Page::new(device, size, size);
+        let (x, y) = page
+            .insert(width, height)
+            .expect("a freshly allocated page must fit the rect that sized it");
+        device.sub_upload_texture(&page.texture, x, y, width, height, image);
+        let rect = page.rect(self.pages.len(), x, y, width, height);
+        self.pages.push(page);
+        rect
+    }
+
+    /// Packs `image` onto a fresh page sized exactly to fit it, which it has
+    /// exclusively: the returned rect always spans the page's full `0..1`
+    /// UV range. Use this for baked font atlases (see the module docs for
+    /// why they can't share a shelf like [`Atlas::insert`]'s images do).
+    pub fn insert_whole(&mut self, device: &D, image: &[u8], width: usize, height: usize) -> AtlasRect {
+        let mut page = Page::new(device, width, height);
+        device.sub_upload_texture(&page.texture, 0, 0, width, height, image);
+        // Mark the page as already full so a later `Atlas::insert` can't
+        // shelf-pack something else into this font's exclusive space.
+        page.next_y = height;
+        let index = self.pages.len();
+        let rect = page.rect(index, 0, 0, width, height);
+        self.pages.push(page);
+        rect
+    }
+
+    /// The GL texture id backing a given page, for binding before a draw call.
+    pub fn page_texture_id(&self, device: &D, page: usize) -> u32 {
+        device.texture_id(&self.pages[page].texture)
+    }
+
+    /// The full pixel dimensions of a page, for translating an [`AtlasRect`]'s
+    /// normalized UVs back into the pixel-space sub-region nuklear's image
+    /// widgets expect.
+    pub fn page_size(&self, page: usize) -> (usize, usize) {
+        (self.pages[page].width, self.pages[page].height)
+    }
+}
@@ -0,0 +1,110 @@
+//! Abstraction over the GPU calls `RenderState`/`Drawer` issue each frame.
+//!
+//! `RenderState`/`Drawer` used to hard-code GLES2 calls directly. Factoring
+//! them behind a `Device` trait (mirroring how pathfinder factors its
+//! rendering into `pathfinder_gpu`) lets this crate target GLES3, desktop GL
+//! or WebGL by swapping the `Device` impl, without touching the nuklear
+//! conversion/draw logic.
+
+use gls::{GLint, GLsizei, GLsizeiptr};
+use std::time::Duration;
+
+/// Blend state the draw loop switches between while submitting a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Straight `SRC_ALPHA`/`ONE_MINUS_SRC_ALPHA` blending used for shapes
+    /// and grayscale-AA text.
+    #[default]
+    Standard,
+    /// Dual-source (`GL_EXT_blend_func_extended`) component-alpha blending
+    /// for subpixel-AA glyphs: `ONE`/`ONE_MINUS_SRC1_COLOR`, so each color
+    /// channel composites against its own coverage weight (written as the
+    /// glyph shader's secondary fragment output) instead of one shared
+    /// `SRC_ALPHA`. The per-channel weights themselves are only an offset-
+    /// sampled approximation of real subpixel coverage — see
+    /// [`TextRendering::SubpixelRGB`](crate::TextRendering::SubpixelRGB) —
+    /// so this still composites in gamma space, not linear.
+    SubpixelText,
+}
+
+/// A GPU backend capable of driving the nuklear draw loop.
+///
+/// [`Gles2Device`](crate::gles2::Gles2Device) reproduces this crate's
+/// original fixed-function-ish GLES2 path. A `Gles3Device` can implement
+/// this trait using VAOs and 32-bit element indices for large vertex
+/// buffers without requiring any change to `RenderState` or `Drawer`.
+pub trait Device {
+    type Buffer;
+    type Program;
+    type Texture;
+    type TimerQuery;
+
+    fn create_vertex_buffer(&self, capacity: usize) -> Self::Buffer;
+    fn create_element_buffer(&self, capacity: usize) -> Self::Buffer;
+    fn stream_buffer(&self, buffer: &Self::Buffer, bytes: &[u8]);
+    /// Discards `buffer`'s previous contents (the GL "orphaning" trick: a
+    /// null-data respecification at the original capacity) so the following
+    /// `stream_buffer` can't stall behind a draw still reading the old data.
+    fn orphan_buffer(&self, buffer: &Self::Buffer, capacity: usize);
+
+    fn create_program(&self, vs_src: &str, fs_src: &str) -> Self::Program;
+    fn attrib_location(&self, program: &Self::Program, name: &str) -> GLint;
+    fn uniform_location(&self, program: &Self::Program, name: &str) -> GLint;
+    fn bind_program(&self, program: &Self::Program);
+    fn set_uniform_mat4(&self, program: &Self::Program, location: GLint, value: &gls::Matrix4);
+    fn set_uniform_sampler(&self, program: &Self::Program, location: GLint, unit: i32);
+    /// Uploads a 2-component float uniform, e.g. the subpixel glyph
+    /// shader's `u_texel` (one texel in normalized UV space).
+    fn set_uniform_vec2(&self, program: &Self::Program, location: GLint, value: (f32, f32));
+
+    fn bind_vertex_buffer(&self, buffer: &Self::Buffer);
+    fn bind_element_buffer(&self, buffer: &Self::Buffer);
+    #[allow(clippy::too_many_arguments)]
+    fn bind_vertex_attribs(
+        &self,
+        position_aloc: GLint,
+        texcoord_aloc: GLint,
+        color_aloc: GLint,
+        stride: GLsizei,
+        position_offset: GLsizeiptr,
+        texcoord_offset: GLsizeiptr,
+        color_offset: GLsizeiptr,
+    );
+
+    fn create_texture(&self, width: usize, height: usize, bytes: &[u8]) -> Self::Texture;
+    #[allow(clippy::too_many_arguments)]
+    fn sub_upload_texture(
+        &self,
+        texture: &Self::Texture,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        bytes: &[u8],
+    );
+    fn texture_id(&self, texture: &Self::Texture) -> u32;
+    fn bind_texture(&self, unit: u32, texture_id: u32);
+
+    fn set_scissor(&self, x: GLint, y: GLint, w: GLsizei, h: GLsizei);
+
+    /// Sets up shared GL state (blending, face culling, the scissor test,
+    /// ...) at the start of a frame, defaulting to [`BlendMode::Standard`].
+    fn begin_frame(&self);
+    /// Switches blend state mid-frame, e.g. when the draw loop moves from
+    /// shapes into subpixel-AA text runs and back.
+    fn set_blend_mode(&self, mode: BlendMode);
+    fn draw_elements(&self, count: GLsizei, offset: GLsizeiptr);
+    /// Restores the GL state `begin_frame` changed.
+    fn end_frame(&self);
+
+    /// Starts a GPU timer query covering the draw calls submitted until the
+    /// matching [`Device::end_timer_query`]. Returns `None` when the backend
+    /// has no timer query support (e.g. `EXT_disjoint_timer_query` is
+    /// absent), in which case callers should skip GPU timing entirely.
+    fn begin_timer_query(&self) -> Option<Self::TimerQuery>;
+    fn end_timer_query(&self, query: &Self::TimerQuery);
+    /// Polls a query without blocking. Query results typically aren't ready
+    /// the same frame they were submitted, so callers should hold on to a
+    /// query for a frame or two before polling it.
+    fn poll_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration>;
+}
@@ -0,0 +1,34 @@
+//! Error type for operations that can fail, such as decoding images.
+
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced by the drawer.
+#[derive(Debug)]
+pub enum Error {
+    /// Decoding an image with the `image` crate failed.
+    Decode(image::ImageError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Decode(err) => write!(f, "failed to decode image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::Decode(err)
+    }
+}
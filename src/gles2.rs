@@ -0,0 +1,204 @@
+//! The default [`Device`] implementation, reproducing the fixed GLES2
+//! pipeline this crate used before the `Device` abstraction existed.
+
+use crate::device::{BlendMode, Device};
+use gls::{gl, prelude::Bindable, GLint, GLsizei, GLsizeiptr, GLuint};
+use std::time::Duration;
+
+/// Drives the `shaders/gles2-*.glsl` shader pair with `u_mvp`/`u_texture`
+/// uniforms and 16-bit element indices, exactly as this crate always has.
+#[derive(Clone, Copy, Default)]
+pub struct Gles2Device;
+
+impl Device for Gles2Device {
+    type Buffer = gls::Buffer;
+    type Program = gls::Program;
+    type Texture = gls::Texture<'static>;
+    type TimerQuery = gls::TimerQuery;
+
+    fn create_vertex_buffer(&self, capacity: usize) -> Self::Buffer {
+        let buffer = gls::Buffer::new_array();
+        buffer.stream_draw_data_null::<u8>(capacity);
+        buffer
+    }
+
+    fn create_element_buffer(&self, capacity: usize) -> Self::Buffer {
+        let buffer = gls::Buffer::new_element_array();
+        buffer.stream_draw_data_null::<u8>(capacity);
+        buffer
+    }
+
+    fn stream_buffer(&self, buffer: &Self::Buffer, bytes: &[u8]) {
+        buffer.update(bytes);
+    }
+
+    fn orphan_buffer(&self, buffer: &Self::Buffer, capacity: usize) {
+        buffer.stream_draw_data_null::<u8>(capacity);
+    }
+
+    fn create_program(&self, vs_src: &str, fs_src: &str) -> Self::Program {
+        gls::Program::from_sources(&[(fs_src, gl::FRAGMENT_SHADER), (vs_src, gl::VERTEX_SHADER)])
+            .unwrap()
+    }
+
+    fn attrib_location(&self, program: &Self::Program, name: &str) -> GLint {
+        program.locate_attrib(name).unwrap_or(-1)
+    }
+
+    fn uniform_location(&self, program: &Self::Program, name: &str) -> GLint {
+        program.locate_uniform(name).unwrap_or(-1)
+    }
+
+    fn bind_program(&self, program: &Self::Program) {
+        program.bind();
+    }
+
+    fn set_uniform_mat4(&self, program: &Self::Program, location: GLint, value: &gls::Matrix4) {
+        program.set_uniform(location, gls::uniform!(mat4(value)));
+    }
+
+    fn set_uniform_sampler(&self, program: &Self::Program, location: GLint, unit: i32) {
+        program.set_uniform(location, gls::uniform!(int(unit)));
+    }
+
+    fn set_uniform_vec2(&self, program: &Self::Program, location: GLint, value: (f32, f32)) {
+        program.set_uniform(location, gls::uniform!(vec2(value.0, value.1)));
+    }
+
+    fn bind_vertex_buffer(&self, buffer: &Self::Buffer) {
+        buffer.bind();
+    }
+
+    fn bind_element_buffer(&self, buffer: &Self::Buffer) {
+        buffer.bind();
+    }
+
+    fn bind_vertex_attribs(
+        &self,
+        position_aloc: GLint,
+        texcoord_aloc: GLint,
+        color_aloc: GLint,
+        stride: GLsizei,
+        position_offset: GLsizeiptr,
+        texcoord_offset: GLsizeiptr,
+        color_offset: GLsizeiptr,
+    ) {
+        gls::VertexAttrib::new(
+            position_aloc as GLuint,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            position_offset,
+        )
+        .bind();
+        gls::VertexAttrib::new(
+            texcoord_aloc as GLuint,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            texcoord_offset,
+        )
+        .bind();
+        gls::VertexAttrib::new(
+            color_aloc as GLuint,
+            4,
+            gl::UNSIGNED_BYTE,
+            gl::TRUE,
+            stride,
+            color_offset,
+        )
+        .bind();
+    }
+
+    fn create_texture(&self, width: usize, height: usize, bytes: &[u8]) -> Self::Texture {
+        gls::TextureLoader::default()
+            .with_bytes(bytes)
+            .with_size(width, height)
+            .with_internal_format(gls::TextureFormat::Rgba)
+            .with_format(gls::TextureFormat::Rgba)
+            .with_linear()
+            .load()
+            .unwrap()
+    }
+
+    fn sub_upload_texture(
+        &self,
+        texture: &Self::Texture,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        bytes: &[u8],
+    ) {
+        texture.sub_upload(x, y, w, h, bytes);
+    }
+
+    fn texture_id(&self, texture: &Self::Texture) -> u32 {
+        texture.id()
+    }
+
+    fn bind_texture(&self, unit: u32, texture_id: u32) {
+        gls::active_texture(gl::TEXTURE0 + unit);
+        gls::bind_texture(gl::TEXTURE_2D, texture_id);
+    }
+
+    fn set_scissor(&self, x: GLint, y: GLint, w: GLsizei, h: GLsizei) {
+        gls::scissor(x, y, w, h);
+    }
+
+    fn begin_frame(&self) {
+        gls::enable(gl::BLEND);
+        self.set_blend_mode(BlendMode::Standard);
+        gls::disable(gl::CULL_FACE);
+        gls::disable(gl::DEPTH_TEST);
+        gls::enable(gl::SCISSOR_TEST);
+        gls::active_texture(gl::TEXTURE0);
+    }
+
+    fn set_blend_mode(&self, mode: BlendMode) {
+        gls::blend_equation(gl::FUNC_ADD);
+        match mode {
+            BlendMode::Standard => {
+                gls::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            }
+            BlendMode::SubpixelText => {
+                // Requires `GL_EXT_blend_func_extended`; paired with the
+                // subpixel glyph shader's `gl_FragColor`/
+                // `gl_SecondaryFragColorEXT` outputs (see
+                // gles2-subpixel-fs.glsl) so each color channel composites
+                // against its own offset-sampled coverage weight, rather
+                // than one shared `SRC_ALPHA`.
+                gls::blend_func(gl::ONE, gl::ONE_MINUS_SRC1_COLOR);
+            }
+        }
+    }
+
+    fn draw_elements(&self, count: GLsizei, offset: GLsizeiptr) {
+        gls::draw_elements(gl::TRIANGLES, count, gl::UNSIGNED_SHORT, offset);
+    }
+
+    fn end_frame(&self) {
+        gls::disable(gl::BLEND);
+        gls::enable(gl::CULL_FACE);
+        gls::enable(gl::DEPTH_TEST);
+        gls::disable(gl::SCISSOR_TEST);
+    }
+
+    fn begin_timer_query(&self) -> Option<Self::TimerQuery> {
+        // Absent when `EXT_disjoint_timer_query` isn't supported by the
+        // current context.
+        let query = gls::TimerQuery::new()?;
+        query.begin();
+        Some(query)
+    }
+
+    fn end_timer_query(&self, query: &Self::TimerQuery) {
+        query.end();
+    }
+
+    fn poll_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration> {
+        query.try_result_ns().map(Duration::from_nanos)
+    }
+}
@@ -1,10 +1,30 @@
-use gls::{gl, prelude::Bindable, GLfloat, GLint, GLsizei, GLsizeiptr, GLubyte, GLuint};
+use gls::{GLfloat, GLint, GLsizei, GLsizeiptr, GLubyte, GLuint};
 use nuki::{
     Allocator, AntiAliasing, Buffer, Context, ConvertConfig, DrawNullTexture,
     DrawVertexLayoutAttribute, DrawVertexLayoutElements, DrawVertexLayoutFormat, FontAtlas,
-    FontAtlasFormat, Handle, Rect,
+    FontAtlasFormat, Handle, Image, Rect,
 };
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+mod atlas;
+mod device;
+mod error;
+mod gles2;
+
+pub use atlas::AtlasRect;
+pub use device::{BlendMode, Device};
+pub use error::{Error, Result};
+pub use gles2::Gles2Device;
+
+use atlas::Atlas;
+
+fn rects_eq(a: &Rect, b: &Rect) -> bool {
+    (a.x - b.x).abs() < f32::EPSILON
+        && (a.y - b.y).abs() < f32::EPSILON
+        && (a.w - b.w).abs() < f32::EPSILON
+        && (a.h - b.h).abs() < f32::EPSILON
+}
 
 macro_rules! offset_of {
     ($t:ty, $field:ident) => {{
@@ -13,64 +33,163 @@ macro_rules! offset_of {
     }};
 }
 
-#[derive(Clone, Default)]
-struct RenderState<'a> {
-    vbo: gls::Buffer,
-    ebo: gls::Buffer,
-    prog: gls::Program,
-    font_texs: Vec<Rc<gls::Texture<'a>>>,
+struct RenderState<D: Device> {
+    device: D,
+    vbo: D::Buffer,
+    ebo: D::Buffer,
+    vbo_capacity: usize,
+    ebo_capacity: usize,
+    prog: D::Program,
+    text_prog: D::Program,
+    atlas: Atlas<D>,
+    image_texs: HashMap<i32, D::Texture>,
+    /// Ids of textures backing baked font atlases, so the draw loop knows
+    /// which runs are eligible for the subpixel glyph shader.
+    glyph_tex_ids: HashSet<u32>,
+    /// `1.0 / (width, height)` of each glyph texture, so the subpixel glyph
+    /// shader can offset-sample neighbouring coverage texels.
+    glyph_tex_texels: HashMap<u32, (f32, f32)>,
     position_aloc: GLint,
     texcoord_aloc: GLint,
     color_aloc: GLint,
     mvp_uloc: GLint,
     texture_uloc: GLint,
+    text_position_aloc: GLint,
+    text_texcoord_aloc: GLint,
+    text_color_aloc: GLint,
+    text_mvp_uloc: GLint,
+    text_texture_uloc: GLint,
+    text_texel_uloc: GLint,
     vs: GLsizei,
-    vp: GLsizei,
-    vt: GLsizei,
-    vc: GLsizei,
+    vp: GLsizeiptr,
+    vt: GLsizeiptr,
+    vc: GLsizeiptr,
 }
 
-impl<'a> RenderState<'a> {
+impl<D: Device + Default> RenderState<D> {
     pub fn new(max_vertex_buffer: usize, max_element_buffer: usize) -> Self {
-        let mut state: Self = Default::default();
-
-        state.vbo = gls::Buffer::new_array();
-        state.vbo.stream_draw_data_null::<u8>(max_vertex_buffer);
-        state.ebo = gls::Buffer::new_element_array();
-        state.ebo.stream_draw_data_null::<u8>(max_element_buffer);
-
-        state.prog = gls::Program::from_sources(&[
-            (include_str!("shaders/gles2-fs.glsl"), gl::FRAGMENT_SHADER),
-            (include_str!("shaders/gles2-vs.glsl"), gl::VERTEX_SHADER),
-        ])
-        .unwrap();
-
-        state.position_aloc = state.prog.locate_attrib("a_position").unwrap_or(-1);
-        state.texcoord_aloc = state.prog.locate_attrib("a_texcoord").unwrap_or(-1);
-        state.color_aloc = state.prog.locate_attrib("a_color").unwrap_or(-1);
-        state.mvp_uloc = state.prog.locate_uniform("u_mvp").unwrap_or(-1);
-        state.texture_uloc = state.prog.locate_uniform("u_texture").unwrap_or(-1);
-
-        state.vs = std::mem::size_of::<Vertex>() as GLsizei;
-        state.vp = offset_of!(Vertex, position) as GLsizei;
-        state.vt = offset_of!(Vertex, uv) as GLsizei;
-        state.vc = offset_of!(Vertex, col) as GLsizei;
-
-        state
+        let device = D::default();
+        let vbo = device.create_vertex_buffer(max_vertex_buffer);
+        let ebo = device.create_element_buffer(max_element_buffer);
+
+        let prog = device.create_program(
+            include_str!("shaders/gles2-vs.glsl"),
+            include_str!("shaders/gles2-fs.glsl"),
+        );
+        let text_prog = device.create_program(
+            include_str!("shaders/gles2-subpixel-vs.glsl"),
+            include_str!("shaders/gles2-subpixel-fs.glsl"),
+        );
+
+        let position_aloc = device.attrib_location(&prog, "a_position");
+        let texcoord_aloc = device.attrib_location(&prog, "a_texcoord");
+        let color_aloc = device.attrib_location(&prog, "a_color");
+        let mvp_uloc = device.uniform_location(&prog, "u_mvp");
+        let texture_uloc = device.uniform_location(&prog, "u_texture");
+
+        let text_position_aloc = device.attrib_location(&text_prog, "a_position");
+        let text_texcoord_aloc = device.attrib_location(&text_prog, "a_texcoord");
+        let text_color_aloc = device.attrib_location(&text_prog, "a_color");
+        let text_mvp_uloc = device.uniform_location(&text_prog, "u_mvp");
+        let text_texture_uloc = device.uniform_location(&text_prog, "u_texture");
+        let text_texel_uloc = device.uniform_location(&text_prog, "u_texel");
+
+        Self {
+            device,
+            vbo,
+            ebo,
+            vbo_capacity: max_vertex_buffer,
+            ebo_capacity: max_element_buffer,
+            prog,
+            text_prog,
+            atlas: Atlas::new(),
+            image_texs: HashMap::new(),
+            glyph_tex_ids: HashSet::new(),
+            glyph_tex_texels: HashMap::new(),
+            position_aloc,
+            texcoord_aloc,
+            color_aloc,
+            mvp_uloc,
+            texture_uloc,
+            text_position_aloc,
+            text_texcoord_aloc,
+            text_color_aloc,
+            text_mvp_uloc,
+            text_texture_uloc,
+            text_texel_uloc,
+            vs: std::mem::size_of::<Vertex>() as GLsizei,
+            vp: offset_of!(Vertex, position) as GLsizeiptr,
+            vt: offset_of!(Vertex, uv) as GLsizeiptr,
+            vc: offset_of!(Vertex, col) as GLsizeiptr,
+        }
     }
 
-    pub fn add_font_texture<'b>(&mut self, image: &'b [u8], width: u32, height: u32) -> Handle {
-        let tex = gls::TextureLoader::default()
-            .with_bytes(image)
-            .with_size(width as usize, height as usize)
-            .with_internal_format(gls::TextureFormat::Rgba)
-            .with_format(gls::TextureFormat::Rgba)
-            .with_linear()
-            .load()
-            .unwrap();
-        let handle = Handle::from_id(tex.id() as i32);
-        self.font_texs.push(Rc::new(tex));
-        handle
+    pub fn add_font_texture(&mut self, image: &[u8], width: u32, height: u32) -> Handle {
+        let rect = self
+            .atlas
+            .insert_whole(&self.device, image, width as usize, height as usize);
+        let tex_id = self.atlas.page_texture_id(&self.device, rect.page);
+        self.glyph_tex_ids.insert(tex_id);
+        self.glyph_tex_texels
+            .insert(tex_id, (1.0 / width as f32, 1.0 / height as f32));
+        Handle::from_id(tex_id as i32)
+    }
+
+    /// Packs `image` into the shared atlas instead of reserving it a whole
+    /// page, returning a [`Handle`] for the page it landed on plus the UV
+    /// sub-rectangle it was packed into. Prefer this over
+    /// [`RenderState::add_font_texture`] for icons and small images so draw
+    /// commands can share texture binds.
+    pub fn add_packed_image(&mut self, image: &[u8], width: u32, height: u32) -> (Handle, AtlasRect) {
+        let rect = self
+            .atlas
+            .insert(&self.device, image, width as usize, height as usize);
+        let handle = Handle::from_id(self.atlas.page_texture_id(&self.device, rect.page) as i32);
+        (handle, rect)
+    }
+
+    /// Builds a nuklear [`Image`] that samples only the sub-rectangle `rect`
+    /// was packed into, translating its normalized UVs back into the
+    /// pixel-space region `nk_subimage_id` expects. Without this, nuklear
+    /// would map an image widget's `0..1` UVs across the *whole* atlas page
+    /// rather than just `rect`'s slice of it.
+    pub fn packed_image(&self, handle: Handle, rect: AtlasRect) -> Image {
+        let (page_w, page_h) = self.atlas.page_size(rect.page);
+        let region = Rect {
+            x: rect.u0 * page_w as f32,
+            y: rect.v0 * page_h as f32,
+            w: (rect.u1 - rect.u0) * page_w as f32,
+            h: (rect.v1 - rect.v0) * page_h as f32,
+        };
+        Image::subimage_id(
+            handle.id().unwrap(),
+            page_w as u16,
+            page_h as u16,
+            region,
+        )
+    }
+
+    /// Decodes an arbitrary encoded image (PNG, JPEG, ...) and uploads it as
+    /// a standalone texture, returning a [`Handle`] usable in nuklear image
+    /// widgets.
+    pub fn load_image(&mut self, bytes: &[u8]) -> Result<Handle> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        let tex = self
+            .device
+            .create_texture(width as usize, height as usize, image.as_raw());
+        let handle = Handle::from_id(self.device.texture_id(&tex) as i32);
+        self.image_texs.insert(handle.id().unwrap(), tex);
+        Ok(handle)
+    }
+
+    /// Frees a texture previously returned by [`RenderState::load_image`].
+    /// Returns `false` if `handle` is unknown.
+    pub fn unload_image(&mut self, handle: Handle) -> bool {
+        match handle.id() {
+            Some(id) => self.image_texs.remove(&id).is_some(),
+            None => false,
+        }
     }
 }
 
@@ -82,6 +201,23 @@ struct Vertex {
     col: [GLubyte; 4],
 }
 
+/// Text antialiasing strategy. See [`DrawOptions::with_text_rendering`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextRendering {
+    /// A single coverage channel per glyph pixel; cheap and works on any
+    /// backend.
+    #[default]
+    Grayscale,
+    /// Approximates per-channel (R/G/B subpixel) glyph coverage by
+    /// horizontally offset-sampling the baked atlas's single grayscale
+    /// coverage channel, composited with a dual-source blend state so each
+    /// channel weights against its own sampled coverage. This is a cosmetic
+    /// approximation of LCD fringing, not true per-subpixel rasterization —
+    /// the font atlas still bakes one coverage value per pixel, not three.
+    /// Requires `GL_EXT_blend_func_extended`.
+    SubpixelRGB,
+}
+
 /// Options to control the drawing.
 #[derive(Clone, Copy, Default, Debug)]
 pub struct DrawOptions {
@@ -89,6 +225,7 @@ pub struct DrawOptions {
     dpi_factor: (f32, f32),
     scale_factor: (f32, f32),
     viewport: (isize, isize, isize, isize),
+    text_rendering: TextRendering,
 }
 
 impl DrawOptions {
@@ -104,6 +241,7 @@ impl DrawOptions {
             dpi_factor: (1.0, 1.0),
             scale_factor: (1.0, 1.0),
             viewport: (0, 0, width as isize, height as isize),
+            text_rendering: TextRendering::Grayscale,
         }
     }
 
@@ -118,10 +256,32 @@ impl DrawOptions {
         self.scale_factor = (x, y);
         self
     }
+
+    /// Select how baked font glyphs are antialiased. See
+    /// [`TextRendering::SubpixelRGB`] for what it does and doesn't deliver.
+    pub fn with_text_rendering(mut self, text_rendering: TextRendering) -> Self {
+        self.text_rendering = text_rendering;
+        self
+    }
+}
+
+/// Per-frame cost reported by [`Drawer::draw_with_stats`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DrawStats {
+    pub vertices: usize,
+    pub elements: usize,
+    pub draw_calls: usize,
+    pub cpu_convert_time: Duration,
+    /// `None` until enough frames have been submitted to have a result
+    /// ready, or if the device has no timer query support.
+    pub gpu_time: Option<Duration>,
 }
 
-#[derive(Clone)]
-pub struct Drawer<'a> {
+/// How many in-flight GPU timer queries to keep before polling the oldest,
+/// so reading a result doesn't stall waiting on the GPU to catch up.
+const TIMER_QUERY_LATENCY: usize = 2;
+
+pub struct Drawer<D: Device = Gles2Device> {
     alloc: Allocator,
     cmds: Buffer,
     vbuf: Buffer,
@@ -129,10 +289,11 @@ pub struct Drawer<'a> {
     config: ConvertConfig,
     vertex_layout: DrawVertexLayoutElements,
     null: DrawNullTexture,
-    state: RenderState<'a>,
+    state: RenderState<D>,
+    pending_queries: VecDeque<D::TimerQuery>,
 }
 
-impl<'a> Drawer<'a> {
+impl<D: Device + Default> Drawer<D> {
     pub fn new(alloc: Allocator, max_vertex_buffer: usize, max_element_buffer: usize) -> Self {
         let vertex_layout = DrawVertexLayoutElements::new(&[
             (
@@ -175,109 +336,313 @@ impl<'a> Drawer<'a> {
             vertex_layout,
             null: Default::default(),
             state: RenderState::new(max_vertex_buffer, max_element_buffer),
+            pending_queries: VecDeque::new(),
         }
     }
 
     /// Draw all elements in the context.
     pub fn draw(&mut self, ctx: &mut Context, options: &DrawOptions) {
-        gls::enable(gl::BLEND);
-        gls::blend_equation(gl::FUNC_ADD);
-        gls::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        gls::disable(gl::CULL_FACE);
-        gls::disable(gl::DEPTH_TEST);
-        gls::enable(gl::SCISSOR_TEST);
-        gls::active_texture(gl::TEXTURE0);
-
-        // Setup program
-        let mvp = self.get_projection(options);
-        self.state.prog.bind();
-        self.state
-            .prog
-            .set_uniform(self.state.mvp_uloc, gls::uniform!(mat4(&mvp)));
-        self.state
-            .prog
-            .set_uniform(self.state.texture_uloc, gls::uniform!(int(0)));
-
-        self.state.vbo.bind();
-        self.state.ebo.bind();
-
-        let a_position = gls::VertexAttrib::new(
-            self.state.position_aloc as GLuint,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            self.state.vs,
-            self.state.vp as GLsizeiptr,
-        );
+        self.record(ctx, options, false);
+    }
 
-        let a_texcoord = gls::VertexAttrib::new(
-            self.state.texcoord_aloc as GLuint,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            self.state.vs,
-            self.state.vt as GLsizeiptr,
-        );
+    /// Like [`Drawer::draw`], but also reports per-frame cost: vertex,
+    /// element and draw-call counts, CPU-side `convert` time, and (when the
+    /// device supports GPU timer queries) GPU submission time sampled a
+    /// couple of frames later so reading it doesn't stall the pipeline.
+    pub fn draw_with_stats(&mut self, ctx: &mut Context, options: &DrawOptions) -> DrawStats {
+        self.record(ctx, options, true)
+    }
 
-        let a_color = gls::VertexAttrib::new(
-            self.state.color_aloc as GLuint,
-            4,
-            gl::UNSIGNED_BYTE,
-            gl::TRUE,
-            self.state.vs,
-            self.state.vc as GLsizeiptr,
-        );
+    fn record(&mut self, ctx: &mut Context, options: &DrawOptions, time_gpu: bool) -> DrawStats {
+        self.state.device.begin_frame();
 
-        a_position.bind();
-        a_texcoord.bind();
-        a_color.bind();
+        let mvp = self.get_projection(options);
+        let mut active_text = false;
+        let mut text_texel_tex: Option<GLuint> = None;
+
+        // Bind the buffers before `use_program`: on GLES2,
+        // `glVertexAttribPointer` (set up inside `use_program`) captures
+        // whichever buffer is currently bound to `GL_ARRAY_BUFFER`, so
+        // binding the VBO afterwards would leave the attribs pointing at
+        // stale or foreign-bound data.
+        self.state.device.bind_vertex_buffer(&self.state.vbo);
+        self.state.device.bind_element_buffer(&self.state.ebo);
+        self.use_program(active_text, &mvp);
 
         self.cmds.clear();
         self.vbuf.clear();
         self.ebuf.clear();
         self.config.set_null(self.null.clone());
 
+        let convert_start = Instant::now();
         ctx.convert(&mut self.cmds, &mut self.vbuf, &mut self.ebuf, &self.config);
+        let cpu_convert_time = convert_start.elapsed();
+
+        let vertices = self.vbuf.as_bytes().len() / std::mem::size_of::<Vertex>();
+        let elements = self.ebuf.as_bytes().len() / std::mem::size_of::<u16>();
 
-        self.state.vbo.update(self.vbuf.as_bytes());
-        self.state.ebo.update(self.ebuf.as_bytes());
+        // Orphan the stores before the sub-update so the driver can hand us
+        // a fresh buffer instead of stalling the pipeline on last frame's
+        // in-flight draw.
+        self.state.device.orphan_buffer(&self.state.vbo, self.state.vbo_capacity);
+        self.state.device.stream_buffer(&self.state.vbo, self.vbuf.as_bytes());
+        self.state.device.orphan_buffer(&self.state.ebo, self.state.ebo_capacity);
+        self.state.device.stream_buffer(&self.state.ebo, self.ebuf.as_bytes());
 
-        let mut eptr: *mut u16 = std::ptr::null_mut();
+        let query = if time_gpu {
+            self.state.device.begin_timer_query()
+        } else {
+            None
+        };
+
+        // Nuklear emits one command per contiguous element range, so
+        // consecutive commands that share a texture, clip rect and glyph/
+        // shape-ness can be merged into a single draw call. Glyph-ness is
+        // part of the merge key (not just texture+clip) because nuklear
+        // bakes its null/white texture into the same atlas page as real
+        // glyphs, so shape fills and text runs can share a texture id and
+        // must not be coalesced across that boundary (see
+        // `command_is_glyph`).
+        let mut eptr: GLsizeiptr = 0;
+        let mut bound_tex: Option<GLuint> = None;
+        let mut draw_calls = 0usize;
+        let mut run: Option<(GLuint, Rect, GLsizeiptr, GLsizei, bool)> = None;
         for cmd in ctx.draw_command_iterator(&self.cmds) {
             if cmd.elem_count() < 1 {
                 continue;
             }
 
-            let count = cmd.elem_count();
-            let id = cmd.texture().id().unwrap();
-            self.clip_rect(cmd.clip_rect(), options);
-            gls::bind_texture(gl::TEXTURE_2D, id as GLuint);
-            gls::draw_elements(
-                gl::TRIANGLES,
-                count as GLsizei,
-                gl::UNSIGNED_SHORT,
-                eptr as GLsizeiptr,
+            let count = cmd.elem_count() as GLsizei;
+            let id = cmd.texture().id().unwrap() as GLuint;
+            let clip = *cmd.clip_rect();
+            let offset = eptr;
+            eptr += count as GLsizeiptr * std::mem::size_of::<u16>() as GLsizeiptr;
+            let is_text = options.text_rendering == TextRendering::SubpixelRGB
+                && self.command_is_glyph(id, offset, count);
+
+            match &mut run {
+                Some((run_id, run_clip, _, run_count, run_text))
+                    if *run_id == id && rects_eq(run_clip, &clip) && *run_text == is_text =>
+                {
+                    *run_count += count;
+                }
+                _ => {
+                    if let Some((run_id, run_clip, run_offset, run_count, run_text)) = run.take() {
+                        self.flush_run(
+                            run_id, &run_clip, run_offset, run_count, run_text, options, &mvp,
+                            &mut bound_tex, &mut active_text, &mut text_texel_tex,
+                        );
+                        draw_calls += 1;
+                    }
+                    run = Some((id, clip, offset, count, is_text));
+                }
+            }
+        }
+        if let Some((run_id, run_clip, run_offset, run_count, run_text)) = run.take() {
+            self.flush_run(
+                run_id, &run_clip, run_offset, run_count, run_text, options, &mvp, &mut bound_tex,
+                &mut active_text, &mut text_texel_tex,
             );
-            eptr = unsafe { eptr.add(count as usize) };
+            draw_calls += 1;
         }
 
-        gls::disable(gl::BLEND);
-        gls::enable(gl::CULL_FACE);
-        gls::enable(gl::DEPTH_TEST);
-        gls::disable(gl::SCISSOR_TEST);
+        if let Some(query) = &query {
+            self.state.device.end_timer_query(query);
+        }
+        if let Some(query) = query {
+            self.pending_queries.push_back(query);
+        }
+
+        self.state.device.end_frame();
+
+        let gpu_time = if time_gpu && self.pending_queries.len() > TIMER_QUERY_LATENCY {
+            self.pending_queries
+                .pop_front()
+                .and_then(|query| self.state.device.poll_timer_query(&query))
+        } else {
+            None
+        };
+
+        DrawStats {
+            vertices,
+            elements,
+            draw_calls,
+            cpu_convert_time,
+            gpu_time,
+        }
     }
 
-    pub fn add_font_texture<'b>(&mut self, data: &'b [u8], width: u32, height: u32) -> Handle {
+    pub fn add_font_texture(&mut self, data: &[u8], width: u32, height: u32) -> Handle {
         self.state.add_font_texture(data, width, height)
     }
 
-    pub fn bake_font_atlas<'b>(&mut self, atlas: &'b mut FontAtlas) -> Handle {
+    /// Packs `data` into the shared glyph/image atlas rather than allocating
+    /// a standalone texture. Prefer this over [`Drawer::add_font_texture`]
+    /// for icons and small images so draw commands can share texture binds.
+    pub fn add_packed_image(&mut self, data: &[u8], width: u32, height: u32) -> (Handle, AtlasRect) {
+        self.state.add_packed_image(data, width, height)
+    }
+
+    /// Builds the nuklear [`Image`] to pass to e.g. `nk_image`/widget
+    /// drawing for a `(handle, rect)` pair returned by
+    /// [`Drawer::add_packed_image`], so its `0..1` UVs land on just that
+    /// sub-rectangle of the shared atlas page instead of the whole page.
+    pub fn packed_image(&self, handle: Handle, rect: AtlasRect) -> Image {
+        self.state.packed_image(handle, rect)
+    }
+
+    /// Decodes an encoded image (PNG, JPEG, ...) with the `image` crate and
+    /// uploads it, returning a [`Handle`] usable in nuklear image widgets.
+    pub fn load_image(&mut self, bytes: &[u8]) -> Result<Handle> {
+        self.state.load_image(bytes)
+    }
+
+    /// Frees a texture previously returned by [`Drawer::load_image`].
+    pub fn unload_image(&mut self, handle: Handle) -> bool {
+        self.state.unload_image(handle)
+    }
+
+    pub fn bake_font_atlas(&mut self, atlas: &mut FontAtlas) -> Handle {
         let (image, w, h) = atlas.bake(FontAtlasFormat::Rgba32);
         let handle = self.add_font_texture(image, w, h);
         atlas.end(handle, Some(&mut self.null));
         handle
     }
 
+    /// Whether the elements at `[offset, offset + count)` in `self.ebuf` are
+    /// a real glyph quad rather than a shape fill sharing the font atlas's
+    /// texture id.
+    ///
+    /// nuklear bakes its null/white texture into the same image as the font
+    /// glyphs and gives it the font atlas's handle (`bake_font_atlas` ->
+    /// `atlas.end(handle, Some(&mut self.null))`), so every solid-color
+    /// shape, window background and line also carries `tex_id` equal to a
+    /// glyph texture's id. Texture id alone can't tell them apart. But a
+    /// shape fill repeats the null texture's single degenerate UV point
+    /// across every vertex, while a glyph quad's four corners span a real
+    /// (non-degenerate) UV rect, so comparing the first two distinct
+    /// vertices' UVs distinguishes them.
+    fn command_is_glyph(&self, tex_id: GLuint, offset: GLsizeiptr, count: GLsizei) -> bool {
+        if count < 2 || !self.state.glyph_tex_ids.contains(&tex_id) {
+            return false;
+        }
+
+        let ebytes = self.ebuf.as_bytes();
+        let vbytes = self.vbuf.as_bytes();
+        let index_at = |i: usize| -> u16 {
+            let start = offset as usize + i * std::mem::size_of::<u16>();
+            u16::from_ne_bytes([ebytes[start], ebytes[start + 1]])
+        };
+        let uv_at = |vertex: u16| -> [f32; 2] {
+            let base = vertex as usize * std::mem::size_of::<Vertex>() + offset_of!(Vertex, uv);
+            [
+                f32::from_ne_bytes(vbytes[base..base + 4].try_into().unwrap()),
+                f32::from_ne_bytes(vbytes[base + 4..base + 8].try_into().unwrap()),
+            ]
+        };
+
+        let first = index_at(0);
+        let first_uv = uv_at(first);
+        (1..count as usize)
+            .map(index_at)
+            .find(|&other| other != first)
+            .map(|other| uv_at(other) != first_uv)
+            .unwrap_or(false)
+    }
+
+    /// Issues one draw call for a run of merged commands that share a
+    /// texture, clip rect and glyph/shape-ness.
+    #[allow(clippy::too_many_arguments)]
+    fn flush_run(
+        &self,
+        tex_id: GLuint,
+        clip: &Rect,
+        offset: GLsizeiptr,
+        count: GLsizei,
+        is_text: bool,
+        options: &DrawOptions,
+        mvp: &gls::Matrix4,
+        bound_tex: &mut Option<GLuint>,
+        active_text: &mut bool,
+        text_texel_tex: &mut Option<GLuint>,
+    ) {
+        if is_text != *active_text {
+            self.use_program(is_text, mvp);
+            *active_text = is_text;
+            // Binding a different program doesn't change which texture unit
+            // is bound, but the blend mode is program-specific.
+            self.state.device.set_blend_mode(if is_text {
+                BlendMode::SubpixelText
+            } else {
+                BlendMode::Standard
+            });
+        }
+
+        self.clip_rect(clip, options);
+        // Commands packed into the same atlas page (or sharing a standalone
+        // texture) share a texture id, so only rebind when it actually
+        // changes between runs.
+        if *bound_tex != Some(tex_id) {
+            self.state.device.bind_texture(0, tex_id);
+            *bound_tex = Some(tex_id);
+        }
+        // Tracked separately from `bound_tex`: a shape run can rebind this
+        // same atlas page's texture without refreshing `u_texel` (it
+        // doesn't use it), so a later text run landing on that
+        // already-bound page would otherwise see a stale texel size from
+        // whatever glyph texture the text program last drew with.
+        if is_text && *text_texel_tex != Some(tex_id) {
+            // The subpixel glyph shader offset-samples neighbouring
+            // coverage texels, so it needs this glyph texture's texel size
+            // whenever the texture it's sampling changes.
+            if let Some(&texel) = self.state.glyph_tex_texels.get(&tex_id) {
+                self.state
+                    .device
+                    .set_uniform_vec2(&self.state.text_prog, self.state.text_texel_uloc, texel);
+            }
+            *text_texel_tex = Some(tex_id);
+        }
+        self.state.device.draw_elements(count, offset);
+    }
+
+    /// Binds the shape or glyph program (and its vertex attrib bindings) and
+    /// uploads its projection/sampler uniforms.
+    fn use_program(&self, text: bool, mvp: &gls::Matrix4) {
+        let state = &self.state;
+        let (prog, mvp_uloc, texture_uloc, position_aloc, texcoord_aloc, color_aloc) = if text {
+            (
+                &state.text_prog,
+                state.text_mvp_uloc,
+                state.text_texture_uloc,
+                state.text_position_aloc,
+                state.text_texcoord_aloc,
+                state.text_color_aloc,
+            )
+        } else {
+            (
+                &state.prog,
+                state.mvp_uloc,
+                state.texture_uloc,
+                state.position_aloc,
+                state.texcoord_aloc,
+                state.color_aloc,
+            )
+        };
+
+        state.device.bind_program(prog);
+        state.device.set_uniform_mat4(prog, mvp_uloc, mvp);
+        state.device.set_uniform_sampler(prog, texture_uloc, 0);
+        state.device.bind_vertex_attribs(
+            position_aloc,
+            texcoord_aloc,
+            color_aloc,
+            state.vs,
+            state.vp,
+            state.vt,
+            state.vc,
+        );
+    }
+
     #[inline]
     pub fn clip_rect(&self, rect: &Rect, options: &DrawOptions) {
         let dw = options.display_size.0 as f32;
@@ -287,7 +652,7 @@ impl<'a> Drawer<'a> {
             && (rect.w - 16384.0).abs() < f32::EPSILON
             && (rect.h - 16384.0).abs() < f32::EPSILON
         {
-            gls::scissor(0, 0, dw as GLsizei, dh as GLsizei);
+            self.state.device.set_scissor(0, 0, dw as GLsizei, dh as GLsizei);
         } else {
             let fx = options.dpi_factor.0 * options.scale_factor.0;
             let fy = options.dpi_factor.1 * options.scale_factor.1;
@@ -295,7 +660,7 @@ impl<'a> Drawer<'a> {
             let w = (rect.w * fx).ceil() as GLsizei;
             let h = (rect.h * fy).ceil() as GLsizei;
             let y = (dh - rect.y - h as f32).floor() as GLint;
-            gls::scissor(x, y, w, h);
+            self.state.device.set_scissor(x, y, w, h);
         }
     }
 